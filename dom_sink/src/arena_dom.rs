@@ -0,0 +1,277 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An owned DOM where every node is allocated from a typed arena.
+//!
+//! Unlike `owned_dom`, this module contains no `unsafe` code: the arena
+//! owns every node for the duration of parsing, each node is a plain
+//! `&'arena Node`, and the parent back-reference and child list live
+//! behind `Cell`/`RefCell`.  Dropping the arena frees the whole tree at
+//! once.  It is the memory-safe counterpart to the transmute-based
+//! `OwnedDom`, with the same `Vec`-of-children shape.
+
+use html5ever::tokenizer::Attribute;
+use html5ever::tree_builder::{TreeSink, QuirksMode, NodeOrText, AppendNode, AppendText};
+use html5ever::tree_builder;
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::default::Default;
+use std::ptr;
+
+use typed_arena::Arena;
+
+use string_cache::QualName;
+use tendril::StrTendril;
+
+/// A node allocated from the arena.  All links are non-owning references
+/// into the same arena, so the tree is acyclic only by construction.
+pub struct Node<'arena> {
+    /// The variant-specific payload of this node.
+    pub data: NodeData,
+    /// The parent, or `None` for the document node and detached nodes.
+    parent: Cell<Option<Ref<'arena>>>,
+    /// This node's children, in document order.
+    children: RefCell<Vec<Ref<'arena>>>,
+}
+
+/// A shared reference to an arena-allocated node.
+pub type Ref<'arena> = &'arena Node<'arena>;
+
+/// A reference to the arena that owns the nodes.
+pub type NodeArena<'arena> = &'arena Arena<Node<'arena>>;
+
+/// The variant data carried by a `Node`.
+///
+/// Text is kept behind a `RefCell` so that `append` can merge adjacent
+/// character data in place without a live mutable borrow of the node.
+pub enum NodeData {
+    Document,
+    Doctype(StrTendril, StrTendril, StrTendril),
+    Text(RefCell<StrTendril>),
+    Comment(StrTendril),
+    Element(QualName, RefCell<Vec<Attribute>>),
+}
+
+impl<'arena> Node<'arena> {
+    fn new(data: NodeData) -> Node<'arena> {
+        Node {
+            data: data,
+            parent: Cell::new(None),
+            children: RefCell::new(vec!()),
+        }
+    }
+
+    /// The parent of this node, if it is attached to one.
+    pub fn parent(&self) -> Option<Ref<'arena>> {
+        self.parent.get()
+    }
+
+    fn get_parent_and_index(&'arena self) -> Option<(Ref<'arena>, usize)> {
+        let parent = match self.parent.get() {
+            Some(parent) => parent,
+            None => return None,
+        };
+        match parent.children.borrow().iter().position(|n| same_node(*n, self)) {
+            Some(i) => Some((parent, i)),
+            None => panic!("have parent but couldn't find in parent's children!"),
+        }
+    }
+}
+
+fn same_node(x: Ref, y: Ref) -> bool {
+    ptr::eq(x, y)
+}
+
+fn append<'arena>(new_parent: Ref<'arena>, child: Ref<'arena>) {
+    assert!(child.parent.get().is_none());
+    child.parent.set(Some(new_parent));
+    new_parent.children.borrow_mut().push(child);
+}
+
+fn append_to_existing_text(prev: Ref, text: &str) -> bool {
+    match prev.data {
+        NodeData::Text(ref existing) => {
+            existing.borrow_mut().push_slice(text);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// A `TreeSink` that builds an arena-backed tree.
+///
+/// The `ArenaSink` borrows the arena for as long as it lives, and every
+/// node handed back through `Self::Handle` borrows it too, so the tree
+/// cannot outlive the arena that owns it.
+pub struct ArenaSink<'arena> {
+    arena: NodeArena<'arena>,
+    document: Ref<'arena>,
+    errors: Vec<Cow<'static, str>>,
+    quirks_mode: QuirksMode,
+}
+
+impl<'arena> ArenaSink<'arena> {
+    /// Create a sink that allocates into `arena`.
+    pub fn new(arena: NodeArena<'arena>) -> ArenaSink<'arena> {
+        ArenaSink {
+            arena: arena,
+            document: arena.alloc(Node::new(NodeData::Document)),
+            errors: vec!(),
+            quirks_mode: tree_builder::NoQuirks,
+        }
+    }
+
+    fn new_node(&self, data: NodeData) -> Ref<'arena> {
+        self.arena.alloc(Node::new(data))
+    }
+
+    fn unparent(&mut self, target: Ref<'arena>) {
+        let (parent, i) = unwrap_or_return!(target.get_parent_and_index(), ());
+        parent.children.borrow_mut().remove(i);
+        target.parent.set(None);
+    }
+
+    /// Consume the sink, returning the root of the finished tree and any
+    /// parse errors.  The arena still owns the nodes; this merely hands
+    /// back a reference into it.
+    pub fn get_result(self) -> (Ref<'arena>, Vec<Cow<'static, str>>) {
+        (self.document, self.errors)
+    }
+}
+
+impl<'arena> TreeSink for ArenaSink<'arena> {
+    type Handle = Ref<'arena>;
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.errors.push(msg);
+    }
+
+    fn get_document(&mut self) -> Ref<'arena> {
+        self.document
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+    }
+
+    fn same_node(&self, x: Ref<'arena>, y: Ref<'arena>) -> bool {
+        same_node(x, y)
+    }
+
+    fn same_home_subtree(&self, _x: Ref<'arena>, _y: Ref<'arena>) -> bool {
+        true
+    }
+
+    fn associate_with_form(&mut self, _target: Ref<'arena>, _form: Ref<'arena>) {
+    }
+
+    fn has_parent_node(&self, node: Ref<'arena>) -> bool {
+        node.parent.get().is_some()
+    }
+
+    fn elem_name(&self, target: Ref<'arena>) -> QualName {
+        match target.data {
+            NodeData::Element(ref name, _) => name.clone(),
+            _ => panic!("not an element!"),
+        }
+    }
+
+    fn create_element(&mut self, name: QualName, attrs: Vec<Attribute>) -> Ref<'arena> {
+        self.new_node(NodeData::Element(name, RefCell::new(attrs)))
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> Ref<'arena> {
+        self.new_node(NodeData::Comment(text))
+    }
+
+    fn append(&mut self, parent: Ref<'arena>, child: NodeOrText<Ref<'arena>>) {
+        // Append to an existing Text node if we have one.
+        match child {
+            AppendText(ref text) => match parent.children.borrow().last() {
+                Some(h) => if append_to_existing_text(*h, &text) { return; },
+                _ => (),
+            },
+            _ => (),
+        }
+
+        append(parent, match child {
+            AppendText(text) => self.new_node(NodeData::Text(RefCell::new(text))),
+            AppendNode(node) => node,
+        });
+    }
+
+    fn append_before_sibling(&mut self,
+            sibling: Ref<'arena>,
+            child: NodeOrText<Ref<'arena>>) {
+        let (parent, i) = sibling.get_parent_and_index()
+            .expect("append_before_sibling called on node without parent");
+
+        let child = match (child, i) {
+            // No previous node.
+            (AppendText(text), 0) => self.new_node(NodeData::Text(RefCell::new(text))),
+
+            // Look for a text node before the insertion point.
+            (AppendText(text), i) => {
+                let prev = parent.children.borrow()[i-1];
+                if append_to_existing_text(prev, &text) {
+                    return;
+                }
+                self.new_node(NodeData::Text(RefCell::new(text)))
+            }
+
+            // The tree builder promises we won't have a text node after
+            // the insertion point.
+
+            // Any other kind of node.
+            (AppendNode(node), _) => node,
+        };
+
+        if child.parent.get().is_some() {
+            self.unparent(child);
+        }
+
+        child.parent.set(Some(parent));
+        parent.children.borrow_mut().insert(i, child);
+    }
+
+    fn append_doctype_to_document(&mut self,
+                                  name: StrTendril,
+                                  public_id: StrTendril,
+                                  system_id: StrTendril) {
+        append(self.document,
+            self.new_node(NodeData::Doctype(name, public_id, system_id)));
+    }
+
+    fn add_attrs_if_missing(&mut self, target: Ref<'arena>, mut attrs: Vec<Attribute>) {
+        let mut existing = match target.data {
+            NodeData::Element(_, ref attrs) => attrs.borrow_mut(),
+            _ => return,
+        };
+
+        // FIXME: quadratic time
+        attrs.retain(|attr|
+            !existing.iter().any(|e| e.name == attr.name));
+        existing.extend(attrs.into_iter());
+    }
+
+    fn remove_from_parent(&mut self, target: Ref<'arena>) {
+        self.unparent(target);
+    }
+
+    fn reparent_children(&mut self, node: Ref<'arena>, new_parent: Ref<'arena>) {
+        let mut children = node.children.borrow_mut();
+        for child in children.iter() {
+            child.parent.set(Some(new_parent));
+        }
+        new_parent.children.borrow_mut().append(&mut *children);
+    }
+
+    fn mark_script_already_started(&mut self, _node: Ref<'arena>) { }
+}
@@ -306,10 +306,116 @@ impl TreeSink for Sink {
 
 pub struct Node {
     pub node: NodeEnum,
-    _parent_not_accessible: usize,
+    /// A non-owning back-reference to the parent, or null for the
+    /// document node.  It occupies the slot previously held by
+    /// `_parent_not_accessible`, and is populated during the final walk
+    /// in `get_result`; since the parent owns this node, the pointer
+    /// stays valid for as long as the node does.
+    parent: *const Node,
     pub children: Vec<Box<Node>>,
 }
 
+/// An iterator over all descendants of a `Node`, in document order.
+pub struct Descendants<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        self.stack.pop().map(|node| {
+            // Push children in reverse so the next `pop` yields the first
+            // child, keeping the traversal in document order.
+            for child in node.children.iter().rev() {
+                self.stack.push(&**child);
+            }
+            node
+        })
+    }
+}
+
+impl Node {
+    /// Iterate over every descendant of this node in document order.
+    /// The node itself is not yielded.
+    pub fn descendants(&self) -> Descendants {
+        let mut stack = Vec::new();
+        for child in self.children.iter().rev() {
+            stack.push(&**child);
+        }
+        Descendants { stack: stack }
+    }
+
+    /// All descendant elements whose tag is `name`.
+    pub fn find_elements<'a>(&'a self, name: &QualName) -> Vec<&'a Node> {
+        self.descendants().filter(|n| match n.node {
+            Element(ref elem_name, _) => elem_name == name,
+            _ => false,
+        }).collect()
+    }
+
+    /// All descendant elements carrying an attribute named `attr_name`.
+    pub fn elements_with_attr<'a>(&'a self, attr_name: &QualName) -> Vec<&'a Node> {
+        self.descendants().filter(|n| match n.node {
+            Element(_, ref attrs) => attrs.iter().any(|a| a.name == *attr_name),
+            _ => false,
+        }).collect()
+    }
+
+    /// All descendant elements with the given local tag name, e.g.
+    /// `node.select("a")` for every anchor in the subtree.
+    pub fn select<'a>(&'a self, tag: &str) -> Vec<&'a Node> {
+        self.descendants().filter(|n| match n.node {
+            Element(ref name, _) => &*name.local == tag,
+            _ => false,
+        }).collect()
+    }
+
+    /// Point every immediate child's `parent` back-reference at this
+    /// node.  Mutation passes that add, move or splice children (the
+    /// sanitizer's unwrap, for instance) must call this afterwards, or a
+    /// reparented child keeps a `parent` aimed at a node that has since
+    /// been dropped and `parent()` dereferences freed memory.
+    pub fn relink_children(&mut self) {
+        let self_ptr: *const Node = self;
+        for child in self.children.iter_mut() {
+            child.parent = self_ptr;
+        }
+    }
+
+    /// This node's parent, or `None` for the document root.
+    pub fn parent(&self) -> Option<&Node> {
+        if self.parent.is_null() {
+            None
+        } else {
+            // Safe: the parent owns us, so it outlives `&self`.
+            Some(unsafe { &*self.parent })
+        }
+    }
+
+    /// Iterate over this node's ancestors, nearest first, up to the
+    /// document root.
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors { next: self.parent() }
+    }
+}
+
+/// An iterator over a `Node`'s ancestors, from parent to root.
+pub struct Ancestors<'a> {
+    next: Option<&'a Node>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        self.next.map(|node| {
+            self.next = node.parent();
+            node
+        })
+    }
+}
+
 pub struct OwnedDom {
     pub document: Box<Node>,
     pub errors: Vec<Cow<'static, str>>,
@@ -323,6 +429,12 @@ impl ParseResult for OwnedDom {
         fn walk(live: &mut HashSet<usize>, node: Handle) {
             live.insert(node.ptr as usize);
             for &child in node.deref().children.iter() {
+                // Establish the parent back-reference that survives the
+                // transmute into `Node`.  The addresses coincide because
+                // `UnsafeCell<SquishyNode>` and `SquishyNode` share a
+                // start, so this `Handle` reads back as a `*const Node`.
+                let mut child = child;
+                child.parent = node;
                 walk(live, child);
             }
         }
@@ -349,7 +461,7 @@ impl ParseResult for OwnedDom {
         };
 
         // FIXME: do this assertion statically
-        let new_addrs = addrs_of!(document => node, _parent_not_accessible, children);
+        let new_addrs = addrs_of!(document => node, parent, children);
         assert_eq!(old_addrs, new_addrs);
 
         OwnedDom {
@@ -0,0 +1,208 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Attribute rewriting, during parsing or as a mutation pass.
+//!
+//! Rather than just dropping attributes, consumers often need to
+//! transform them — renaming `src` to `data-source`, or routing a URL
+//! through a proxy.  A caller-supplied `FnMut(&QualName, &mut
+//! Vec<Attribute>)` sees every element with full `QualName` context and
+//! may rename, remap, drop or add attributes.  The same callback can be
+//! applied up-front through `RewriteSink` (which wraps any `TreeSink`)
+//! or afterwards with `rewrite_attrs` over an owned tree.
+
+use common::Element;
+use owned_dom::Node;
+
+use html5ever::tokenizer::Attribute;
+use html5ever::tree_builder::{TreeSink, QuirksMode, NodeOrText};
+
+use std::ascii::AsciiExt;
+use std::borrow::Cow;
+
+use string_cache::QualName;
+use tendril::StrTendril;
+
+/// Visit `node` and every element below it in document order, handing each
+/// element's name and attribute list to `f` for in-place rewriting.
+pub fn rewrite_attrs<F>(node: &mut Node, f: &mut F)
+    where F: FnMut(&QualName, &mut Vec<Attribute>) {
+    if let Element(ref name, ref mut attrs) = node.node {
+        f(name, attrs);
+    }
+    for child in node.children.iter_mut() {
+        rewrite_attrs(child, f);
+    }
+}
+
+/// A `TreeSink` that runs a rewrite callback on every element's
+/// attributes as it is created, then delegates to an inner sink.
+pub struct RewriteSink<S, F> {
+    /// The wrapped sink that actually builds the tree.
+    pub inner: S,
+    /// The per-element attribute rewriter.
+    pub rewrite: F,
+}
+
+impl<S, F> RewriteSink<S, F>
+    where S: TreeSink, F: FnMut(&QualName, &mut Vec<Attribute>) {
+    /// Wrap `inner`, invoking `rewrite` for each element created.
+    pub fn new(inner: S, rewrite: F) -> RewriteSink<S, F> {
+        RewriteSink { inner: inner, rewrite: rewrite }
+    }
+}
+
+impl<S, F> TreeSink for RewriteSink<S, F>
+    where S: TreeSink, F: FnMut(&QualName, &mut Vec<Attribute>) {
+    type Handle = S::Handle;
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.inner.parse_error(msg);
+    }
+
+    fn get_document(&mut self) -> S::Handle {
+        self.inner.get_document()
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.inner.set_quirks_mode(mode);
+    }
+
+    fn same_node(&self, x: S::Handle, y: S::Handle) -> bool {
+        self.inner.same_node(x, y)
+    }
+
+    fn same_home_subtree(&self, x: S::Handle, y: S::Handle) -> bool {
+        self.inner.same_home_subtree(x, y)
+    }
+
+    fn associate_with_form(&mut self, target: S::Handle, form: S::Handle) {
+        self.inner.associate_with_form(target, form);
+    }
+
+    fn has_parent_node(&self, node: S::Handle) -> bool {
+        self.inner.has_parent_node(node)
+    }
+
+    fn elem_name(&self, target: S::Handle) -> QualName {
+        self.inner.elem_name(target)
+    }
+
+    fn create_element(&mut self, name: QualName, mut attrs: Vec<Attribute>) -> S::Handle {
+        (self.rewrite)(&name, &mut attrs);
+        self.inner.create_element(name, attrs)
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> S::Handle {
+        self.inner.create_comment(text)
+    }
+
+    fn append(&mut self, parent: S::Handle, child: NodeOrText<S::Handle>) {
+        self.inner.append(parent, child);
+    }
+
+    fn append_before_sibling(&mut self, sibling: S::Handle, child: NodeOrText<S::Handle>) {
+        self.inner.append_before_sibling(sibling, child);
+    }
+
+    fn append_doctype_to_document(&mut self,
+                                  name: StrTendril,
+                                  public_id: StrTendril,
+                                  system_id: StrTendril) {
+        self.inner.append_doctype_to_document(name, public_id, system_id);
+    }
+
+    fn add_attrs_if_missing(&mut self, target: S::Handle, attrs: Vec<Attribute>) {
+        self.inner.add_attrs_if_missing(target, attrs);
+    }
+
+    fn remove_from_parent(&mut self, target: S::Handle) {
+        self.inner.remove_from_parent(target);
+    }
+
+    fn reparent_children(&mut self, node: S::Handle, new_parent: S::Handle) {
+        self.inner.reparent_children(node, new_parent);
+    }
+
+    fn mark_script_already_started(&mut self, node: S::Handle) {
+        self.inner.mark_script_already_started(node);
+    }
+}
+
+/// Whether `value` is an absolute URL whose scheme equals `scheme`,
+/// compared case-insensitively so `HTTP:`/`Https:` match `http`/`https`.
+/// A value with no scheme (relative or protocol-relative) never matches.
+fn value_has_scheme(value: &str, scheme: &str) -> bool {
+    match value.find(':') {
+        Some(i) => value[..i].eq_ignore_ascii_case(scheme),
+        None => false,
+    }
+}
+
+/// A single rewrite operation, as assembled by `AttrRewriter`.
+enum Op {
+    Rename(QualName, QualName),
+    ProxyUrl(QualName, String, String),
+}
+
+/// A convenience builder for the most common rewrites.  It does not
+/// implement `FnMut` itself; pass a closure that calls `apply`, e.g.
+/// `|n, a| rewriter.apply(n, a)`, to `rewrite_attrs` or `RewriteSink`.
+pub struct AttrRewriter {
+    ops: Vec<Op>,
+}
+
+impl AttrRewriter {
+    /// An empty rewriter, performing no changes.
+    pub fn new() -> AttrRewriter {
+        AttrRewriter { ops: vec!() }
+    }
+
+    /// Rename every occurrence of attribute `from` to `to`.
+    pub fn rename(mut self, from: QualName, to: QualName) -> AttrRewriter {
+        self.ops.push(Op::Rename(from, to));
+        self
+    }
+
+    /// Prefix the value of `attr` with `prefix` whenever its URL uses
+    /// `scheme` (e.g. route `http:`/`https:` links through a proxy).  The
+    /// scheme is matched case-insensitively (`HTTP:` matches `http`), but
+    /// only absolute URLs are rewritten: scheme-less, relative and
+    /// protocol-relative (`//host/…`) values are left untouched, so a
+    /// caller proxying those must normalize them first.
+    pub fn proxy_url(mut self, attr: QualName, scheme: &str, prefix: &str) -> AttrRewriter {
+        self.ops.push(Op::ProxyUrl(attr, scheme.to_owned(), prefix.to_owned()));
+        self
+    }
+
+    /// Apply every configured operation to one element's attributes.
+    pub fn apply(&mut self, _name: &QualName, attrs: &mut Vec<Attribute>) {
+        for op in self.ops.iter() {
+            match *op {
+                Op::Rename(ref from, ref to) => {
+                    for attr in attrs.iter_mut() {
+                        if attr.name == *from {
+                            attr.name = to.clone();
+                        }
+                    }
+                }
+                Op::ProxyUrl(ref name, ref scheme, ref prefix) => {
+                    for attr in attrs.iter_mut() {
+                        if attr.name == *name
+                            && value_has_scheme(&attr.value, scheme) {
+                            let mut value = StrTendril::from(&prefix[..]);
+                            value.push_tendril(&attr.value);
+                            attr.value = value;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An allowlist sanitizer for the owned DOM.
+//!
+//! A `SanitizeConfig` is walked over an `OwnedDom` before it is handed
+//! back or serialized, dropping elements and attributes that aren't on
+//! the allowlist and stripping URL-bearing attributes whose scheme isn't
+//! permitted.  The cleaned tree feeds the existing `Serializable for
+//! Node` impl unchanged.
+
+use common::Element;
+use owned_dom::{Node, OwnedDom};
+
+use html5ever::tokenizer::Attribute;
+
+use std::ascii::AsciiExt;
+use std::collections::{HashMap, HashSet};
+use std::default::Default;
+
+use string_cache::QualName;
+use tendril::StrTendril;
+
+/// What to do with an element whose tag isn't on the allowlist.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ElementPolicy {
+    /// Drop the element and everything below it.
+    Drop,
+    /// Drop the element but splice its children into the parent in place.
+    Unwrap,
+}
+
+/// The set of elements, attributes and URL schemes the sanitizer will
+/// keep, plus what to do with everything else.
+pub struct SanitizeConfig {
+    /// Elements that are allowed to remain in the tree.
+    pub allowed_elements: HashSet<QualName>,
+    /// For each allowed element, the attribute names that may stay.
+    pub allowed_attributes: HashMap<QualName, HashSet<QualName>>,
+    /// URL schemes (lower-case, without the trailing colon) permitted in
+    /// URL-bearing attributes such as `href` and `src`.
+    pub allowed_schemes: HashSet<String>,
+    /// What to do with an element that isn't in `allowed_elements`.
+    pub disallowed: ElementPolicy,
+    /// If set, add `rel="noopener noreferrer"` to any `<a>` that has a
+    /// `target` attribute and no `rel` left after attribute filtering; an
+    /// allowed existing `rel` is kept as it is.
+    pub add_noopener: bool,
+}
+
+impl Default for SanitizeConfig {
+    fn default() -> SanitizeConfig {
+        SanitizeConfig {
+            allowed_elements: HashSet::new(),
+            allowed_attributes: HashMap::new(),
+            allowed_schemes: HashSet::new(),
+            disallowed: ElementPolicy::Drop,
+            add_noopener: false,
+        }
+    }
+}
+
+/// The attribute names whose values are URLs and therefore scheme-checked.
+fn is_url_attr(name: &QualName) -> bool {
+    match &*name.local {
+        "href" | "src" | "action" | "background" | "cite" | "longdesc"
+            | "poster" | "formaction" => true,
+        _ => false,
+    }
+}
+
+fn is_anchor(name: &QualName) -> bool {
+    &*name.local == "a"
+}
+
+/// How a URL value's leading component classifies once it has been
+/// normalized the way a browser would normalize it before resolving.
+enum UrlScheme<'a> {
+    /// An absolute URL bearing the given scheme (without the colon).
+    Absolute(&'a str),
+    /// A protocol-relative URL (`//host/path`): it inherits the page's
+    /// scheme, so its effective scheme can't be vetted here.
+    ProtocolRelative,
+    /// A genuine relative URL with no scheme.
+    Relative,
+    /// A value with a colon whose leading component is empty or doesn't
+    /// begin with an ASCII letter, such as `:alert(1)` or `1x:…`.
+    Malformed,
+}
+
+/// `true` for the C0 control characters and space that a browser strips
+/// from the ends of a URL.  The URL spec trims every leading/trailing
+/// byte in `0x00..=0x20`, so a leading `\x01` in front of `javascript:`
+/// must not shield the scheme from detection.
+fn is_url_trim(c: char) -> bool {
+    (c as u32) <= 0x20
+}
+
+/// Normalize a URL value the way a browser does before resolving it:
+/// trim leading and trailing C0-control/space characters and drop any
+/// embedded tab, carriage-return or line-feed.  Without this,
+/// ` javascript:alert(1)`, `\x01javascript:…` and `java\tscript:…` all
+/// slip past a naive scheme check yet still execute once the browser
+/// cleans them up.
+fn normalize_url(value: &str) -> String {
+    value.trim_matches(is_url_trim)
+        .chars()
+        .filter(|&c| c != '\t' && c != '\n' && c != '\r')
+        .collect()
+}
+
+/// Classify a normalized URL value by inspecting its leading component.
+/// Follows the URL grammar: a scheme is an ASCII letter followed by
+/// letters, digits, `+`, `-` or `.`, terminated by `:`.
+fn classify_scheme(value: &str) -> UrlScheme {
+    let bytes = value.as_bytes();
+    // A browser treats backslashes in special-scheme URLs as slashes, so
+    // `\\evil.com`, `/\evil.com` and `//evil.com` are all protocol-
+    // relative and must be decided as such rather than falling through to
+    // the relative-URL path.
+    let is_slash = |b: u8| b == b'/' || b == b'\\';
+    if bytes.len() >= 2 && is_slash(bytes[0]) && is_slash(bytes[1]) {
+        return UrlScheme::ProtocolRelative;
+    }
+    for (i, &b) in bytes.iter().enumerate() {
+        match b as char {
+            // A colon closes the scheme.  It's a valid scheme only when
+            // it is non-empty and began with an ASCII letter; otherwise
+            // the value is deliberately malformed.
+            ':' => return if i > 0 && bytes[0].is_ascii()
+                    && (bytes[0] as char).is_alphabetic() {
+                UrlScheme::Absolute(&value[..i])
+            } else {
+                UrlScheme::Malformed
+            },
+            // A path/query/fragment delimiter (or backslash, which the
+            // browser folds to a slash) before any colon means the value
+            // never had a scheme.
+            '/' | '\\' | '?' | '#' => return UrlScheme::Relative,
+            c if c.is_alphanumeric() || c == '+' || c == '-' || c == '.' => {},
+            // Any other character before a colon breaks the scheme, so
+            // what remains is a relative reference.
+            _ => return UrlScheme::Relative,
+        }
+    }
+    UrlScheme::Relative
+}
+
+impl SanitizeConfig {
+    fn scheme_allowed(&self, value: &StrTendril) -> bool {
+        let normalized = normalize_url(value);
+        match classify_scheme(&normalized) {
+            // A genuine relative URL carries no scheme and is allowed.
+            UrlScheme::Relative => true,
+            UrlScheme::Absolute(scheme) => self.allowed_schemes.iter()
+                .any(|s| s.eq_ignore_ascii_case(scheme)),
+            // Protocol-relative URLs smuggle in an arbitrary origin under
+            // the page's scheme, and a malformed scheme can't be vetted
+            // either — default-deny both rather than silently keeping them.
+            UrlScheme::ProtocolRelative | UrlScheme::Malformed => false,
+        }
+    }
+
+    fn clean_attrs(&self, name: &QualName, attrs: &mut Vec<Attribute>) {
+        let allowed = self.allowed_attributes.get(name);
+        attrs.retain(|attr| match allowed {
+            Some(set) => set.contains(&attr.name),
+            None => false,
+        });
+        attrs.retain(|attr|
+            !is_url_attr(&attr.name) || self.scheme_allowed(&attr.value));
+
+        if self.add_noopener && is_anchor(name)
+            && attrs.iter().any(|a| &*a.name.local == "target")
+            && !attrs.iter().any(|a| &*a.name.local == "rel") {
+            attrs.push(Attribute {
+                name: QualName::new(ns!(""), atom!("rel")),
+                value: "noopener noreferrer".into(),
+            });
+        }
+    }
+
+    /// Clean a single node's children in place, recursing depth-first.
+    pub fn clean_node(&self, node: &mut Node) {
+        let mut kept = Vec::with_capacity(node.children.len());
+        for mut child in node.children.drain(..) {
+            self.clean_node(&mut child);
+
+            let allowed = match child.node {
+                Element(ref name, _) => self.allowed_elements.contains(name),
+                _ => true,
+            };
+
+            if allowed {
+                if let Element(ref name, ref mut attrs) = child.node {
+                    self.clean_attrs(name, attrs);
+                }
+                kept.push(child);
+            } else {
+                match self.disallowed {
+                    ElementPolicy::Drop => {}
+                    ElementPolicy::Unwrap => kept.extend(child.children.drain(..)),
+                }
+            }
+        }
+        node.children = kept;
+        // Dropping and splicing above left some surviving children with a
+        // `parent` pointing at a node that no longer owns them; re-point
+        // them so `parent()`/`ancestors()` stay sound on the cleaned tree.
+        node.relink_children();
+    }
+
+    /// Clean an entire parsed document in place.
+    pub fn clean(&self, dom: &mut OwnedDom) {
+        self.clean_node(&mut dom.document);
+    }
+}